@@ -0,0 +1,129 @@
+use super::filter::ActiveVoxels;
+use super::geometry::{box_boundary, face_neighbors};
+use crate::voxel::Vec3i;
+use std::collections::{HashSet, VecDeque};
+
+/// Finds every narrow-band voxel classified as outside (positive sign) that
+/// the BFS, started from the grid's bounding-box boundary and only allowed to
+/// step through other positive voxels (tiles included), never reaches. These
+/// are sealed air pockets: outside-classified by value, but walled off by
+/// solid (negative) voxels from the true exterior. Unreached voxels inside
+/// uniform background tiles are not returned.
+pub(super) fn find_sealed_cavities(active: &ActiveVoxels, min: Vec3i, max: Vec3i) -> HashSet<Vec3i> {
+    let is_outside = |idx: &Vec3i| active.get(idx).unwrap_or(1.0) > 0.0;
+
+    let mut reached = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    for idx in box_boundary(min, max) {
+        if is_outside(&idx) && reached.insert(idx) {
+            queue.push_back(idx);
+        }
+    }
+
+    while let Some(idx) = queue.pop_front() {
+        for offset in face_neighbors() {
+            let neighbor = idx + offset;
+            if neighbor.x < min.x
+                || neighbor.y < min.y
+                || neighbor.z < min.z
+                || neighbor.x > max.x
+                || neighbor.y > max.y
+                || neighbor.z > max.z
+            {
+                continue;
+            }
+
+            if reached.contains(&neighbor) || !is_outside(&neighbor) {
+                continue;
+            }
+
+            reached.insert(neighbor);
+            queue.push_back(neighbor);
+        }
+    }
+
+    active
+        .keys()
+        .filter(|idx| is_outside(idx) && !reached.contains(*idx))
+        .copied()
+        .collect()
+}
+
+/// Counts the voxel faces shared between a solid (negative) voxel and a
+/// sealed cavity voxel, i.e. the surface area enclosing trapped air pockets.
+pub(super) fn count_cavity_faces(active: &ActiveVoxels, cavities: &HashSet<Vec3i>) -> usize {
+    let mut faces = 0;
+    for idx in cavities {
+        for offset in face_neighbors() {
+            let neighbor = idx + offset;
+            let is_solid = active.get(&neighbor).unwrap_or(1.0) <= 0.0;
+            if is_solid {
+                faces += 1;
+            }
+        }
+    }
+    faces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::filter::collect_active;
+    use super::super::Volume;
+    use crate::helpers::aliases::Vec3f;
+    use crate::voxel::Vec3i;
+
+    /// A solid shell (outer sphere minus inner sphere), which seals off the
+    /// inner sphere's interior as a trapped, "outside"-classified air pocket.
+    fn hollow_sphere(outer_radius: f32, inner_radius: f32) -> Volume {
+        let voxel_size = 0.5;
+        let extent = outer_radius + 1.0;
+        let min = Vec3f::new(-extent, -extent, -extent);
+        let max = Vec3f::new(extent, extent, extent);
+
+        let outer = Volume::from_fn(voxel_size, min, max, 4, |p| p.norm() - outer_radius);
+        let inner = Volume::from_fn(voxel_size, min, max, 4, |p| p.norm() - inner_radius);
+
+        outer.subtract(inner)
+    }
+
+    #[test]
+    fn fill_cavities_flips_a_sealed_air_pocket_to_solid() {
+        let shell = hollow_sphere(3.0, 1.0);
+
+        let before = collect_active(shell.grid());
+        assert!(
+            before.get(&Vec3i::new(0, 0, 0)).unwrap_or(1.0) > 0.0,
+            "the inner sphere's center should start out classified as outside, walled in by the shell"
+        );
+
+        let filled = shell.fill_cavities();
+        let after = collect_active(filled.grid());
+
+        assert!(
+            after.get(&Vec3i::new(0, 0, 0)).unwrap_or(1.0) < 0.0,
+            "the sealed cavity at the center should have been flipped to solid"
+        );
+    }
+
+    #[test]
+    fn interior_surface_area_matches_the_sealed_sphere_it_encloses() {
+        let inner_radius = 1.0;
+        let shell = hollow_sphere(3.0, inner_radius);
+
+        let area = shell.interior_surface_area();
+        let expected = 4.0 * std::f32::consts::PI * inner_radius * inner_radius;
+
+        // Voxelizing a sphere's surface as unit grid faces is inherently a
+        // coarse approximation, so this only checks the right ballpark, not
+        // an exact match.
+        assert!(
+            area > 0.0,
+            "a real sealed cavity should report nonzero enclosing surface area"
+        );
+        assert!(
+            (area - expected).abs() < expected,
+            "enclosing surface area {area} should be within the same order of magnitude as the sphere's {expected}"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Small index-space geometry helpers shared by the narrow-band algorithms
+//! in this module (cavity sealing, filtering): a voxel's face-adjacent
+//! neighbors, and every voxel on the six faces of an axis-aligned box.
+
+use crate::voxel::Vec3i;
+
+pub(super) fn face_neighbors() -> [Vec3i; 6] {
+    [
+        Vec3i::new(1, 0, 0),
+        Vec3i::new(-1, 0, 0),
+        Vec3i::new(0, 1, 0),
+        Vec3i::new(0, -1, 0),
+        Vec3i::new(0, 0, 1),
+        Vec3i::new(0, 0, -1),
+    ]
+}
+
+/// Every voxel index on the six faces of the axis-aligned box `min..=max`,
+/// used to seed a flood fill from "the true exterior" without visiting the
+/// box's interior first.
+pub(super) fn box_boundary(min: Vec3i, max: Vec3i) -> Vec<Vec3i> {
+    let mut boundary = Vec::new();
+
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            boundary.push(Vec3i::new(x, y, min.z));
+            boundary.push(Vec3i::new(x, y, max.z));
+        }
+    }
+    for x in min.x..=max.x {
+        for z in min.z..=max.z {
+            boundary.push(Vec3i::new(x, min.y, z));
+            boundary.push(Vec3i::new(x, max.y, z));
+        }
+    }
+    for y in min.y..=max.y {
+        for z in min.z..=max.z {
+            boundary.push(Vec3i::new(min.x, y, z));
+            boundary.push(Vec3i::new(max.x, y, z));
+        }
+    }
+
+    boundary
+}
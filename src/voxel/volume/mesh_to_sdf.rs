@@ -0,0 +1,299 @@
+use crate::helpers::aliases::Vec3f;
+use crate::voxel::Vec3i;
+use std::collections::HashMap;
+
+/// Per-triangle scan: the set of voxel indices within `band` of the closest
+/// point on `triangle`, together with the unsigned distance at each index.
+/// Callers fold these into a grid-wide map keeping the minimum magnitude.
+pub(super) fn rasterize_triangle(
+    triangle: (Vec3f, Vec3f, Vec3f),
+    voxel_size: f32,
+    band: f32,
+    out: &mut HashMap<Vec3i, f32>,
+) {
+    let (a, b, c) = triangle;
+
+    let tri_min = a.inf(&b).inf(&c);
+    let tri_max = a.sup(&b).sup(&c);
+
+    let padding = Vec3f::new(band, band, band);
+    let min_idx = ((tri_min - padding) / voxel_size).map(|x| x.floor() as isize);
+    let max_idx = ((tri_max + padding) / voxel_size).map(|x| x.ceil() as isize);
+
+    for x in min_idx.x..=max_idx.x {
+        for y in min_idx.y..=max_idx.y {
+            for z in min_idx.z..=max_idx.z {
+                let idx = Vec3i::new(x, y, z);
+                let center = idx.cast() * voxel_size;
+                let closest = closest_point_on_triangle(&center, &a, &b, &c);
+                let dist = (center - closest).norm();
+
+                if !dist.is_finite() || dist > band {
+                    continue;
+                }
+
+                out.entry(idx)
+                    .and_modify(|d| *d = d.min(dist))
+                    .or_insert(dist);
+            }
+        }
+    }
+}
+
+/// Closest point to `p` on triangle `abc`, clamping the barycentric
+/// projection of `p` onto the triangle's plane to the triangle region.
+/// Follows the standard region-based test (Ericson, *Real-Time Collision
+/// Detection*, section 5.1.5).
+///
+/// Degenerate faces (coincident vertices, collinear points) are tolerated:
+/// each edge-region division below is guarded against its zero-length-edge
+/// case, falling back to the edge's own endpoint. Without that guard a
+/// zero-length edge (e.g. two coincident vertices) drives the division to
+/// `0.0 / 0.0`, and the resulting NaN "closest point" isn't caught by
+/// `rasterize_triangle`'s band check (NaN comparisons are always `false`),
+/// poisoning the output map from a single degenerate triangle.
+fn closest_point_on_triangle(p: &Vec3f, a: &Vec3f, b: &Vec3f, c: &Vec3f) -> Vec3f {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let denom = d1 - d3;
+        if denom == 0.0 {
+            return *a;
+        }
+        return a + ab * (d1 / denom);
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let denom = d2 - d6;
+        if denom == 0.0 {
+            return *a;
+        }
+        return a + ac * (d2 / denom);
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let denom = (d4 - d3) + (d5 - d6);
+        if denom == 0.0 {
+            return *b;
+        }
+        return b + (c - b) * ((d4 - d3) / denom);
+    }
+
+    let denom = va + vb + vc;
+    if denom == 0.0 {
+        return *a;
+    }
+    let inv_denom = 1.0 / denom;
+    let v = vb * inv_denom;
+    let w = vc * inv_denom;
+    a + ab * v + ac * w
+}
+
+/// Re-signs every band voxel by the parity of its intersections with the
+/// source mesh along a ray cast in `+x`: for a given `(y, z)` column, sort
+/// the mesh's x-crossings and count how many lie on the near side of the
+/// voxel — an even count means we haven't passed through the surface yet
+/// (outside), odd means we have (inside).
+///
+/// This replaces a purely local heuristic ("unsigned distance increased
+/// across a step, so we must have crossed the surface") that breaks down
+/// whenever a path through the band passes more than one true crossing —
+/// e.g. two nearby, disjoint surface patches with an open gap between them
+/// look, one step at a time, identical to a single surface with solid
+/// behind it: both are "distance went up". Counting actual intersections
+/// against the mesh has no such ambiguity, since it doesn't infer crossings
+/// from distance, it counts them directly.
+pub(super) fn sign_narrow_band(band: &mut HashMap<Vec3i, f32>, triangles: &[(Vec3f, Vec3f, Vec3f)]) {
+    let mut columns: HashMap<(isize, isize), Vec<f32>> = HashMap::new();
+    for idx in band.keys() {
+        columns.entry((idx.y, idx.z)).or_default();
+    }
+
+    for (&(y, z), crossings) in columns.iter_mut() {
+        for (a, b, c) in triangles {
+            if let Some(x) = vertical_ray_crossing(y as f32, z as f32, *a, *b, *c) {
+                crossings.push(x);
+            }
+        }
+        crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        // A ray through an edge or vertex shared by several triangles hits
+        // each of them at the same x: that is one crossing, not several.
+        crossings.dedup();
+    }
+
+    for (idx, dist) in band.iter_mut() {
+        let crossings = &columns[&(idx.y, idx.z)];
+        let inside = crossings.iter().filter(|&&x| x < idx.x as f32).count() % 2 == 1;
+        *dist = if inside { -dist.abs() } else { dist.abs() };
+    }
+}
+
+/// Where the vertical ray `{ (x, y, z) : x in R }` pierces the plane of
+/// triangle `abc`, if it passes through the triangle's interior at all.
+/// Found via the barycentric coordinates of `(y, z)` in the triangle's
+/// projection onto the yz plane: if all three weights land in `[0, 1]`, the
+/// ray hits the triangle, and those same weights applied to the vertices'
+/// x coordinates give the crossing point. Degenerate triangles (collinear
+/// or coincident vertices, including when the triangle is edge-on to the
+/// ray) make the projected area zero, reported as "no crossing" rather than
+/// dividing by zero.
+fn vertical_ray_crossing(y: f32, z: f32, a: Vec3f, b: Vec3f, c: Vec3f) -> Option<f32> {
+    let (v0y, v0z) = (b.y - a.y, b.z - a.z);
+    let (v1y, v1z) = (c.y - a.y, c.z - a.z);
+    let (v2y, v2z) = (y - a.y, z - a.z);
+
+    let d00 = v0y * v0y + v0z * v0z;
+    let d01 = v0y * v1y + v0z * v1z;
+    let d11 = v1y * v1y + v1z * v1z;
+    let d20 = v2y * v0y + v2z * v0z;
+    let d21 = v2y * v1y + v2z * v1z;
+
+    let denom = d00 * d11 - d01 * d01;
+    if denom == 0.0 {
+        return None;
+    }
+
+    let v = (d11 * d20 - d01 * d21) / denom;
+    let w = (d00 * d21 - d01 * d20) / denom;
+    let u = 1.0 - v - w;
+
+    if u < 0.0 || v < 0.0 || w < 0.0 {
+        return None;
+    }
+
+    Some(u * a.x + v * b.x + w * c.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(a: Vec3f, b: Vec3f, c: Vec3f, d: Vec3f) -> [(Vec3f, Vec3f, Vec3f); 2] {
+        [(a, b, c), (a, c, d)]
+    }
+
+    #[test]
+    fn sign_narrow_band_signs_both_sides_of_a_hollow_cube() {
+        // A hollow unit cube, (-1, -1, -1) to (1, 1, 1), six quads (two
+        // triangles each). Voxels strictly inside should come out negative,
+        // voxels outside positive, regardless of how far apart the two
+        // cube faces bounding a given ray are — there's no local band
+        // geometry to mislead a distance-magnitude heuristic here, only
+        // actual triangle crossings.
+        let lo = Vec3f::new(-1.0, -1.0, -1.0);
+        let hi = Vec3f::new(1.0, 1.0, 1.0);
+        let mut triangles = Vec::new();
+        triangles.extend(quad(
+            Vec3f::new(lo.x, lo.y, lo.z),
+            Vec3f::new(lo.x, hi.y, lo.z),
+            Vec3f::new(lo.x, hi.y, hi.z),
+            Vec3f::new(lo.x, lo.y, hi.z),
+        ));
+        triangles.extend(quad(
+            Vec3f::new(hi.x, lo.y, lo.z),
+            Vec3f::new(hi.x, lo.y, hi.z),
+            Vec3f::new(hi.x, hi.y, hi.z),
+            Vec3f::new(hi.x, hi.y, lo.z),
+        ));
+        triangles.extend(quad(
+            Vec3f::new(lo.x, lo.y, lo.z),
+            Vec3f::new(lo.x, lo.y, hi.z),
+            Vec3f::new(hi.x, lo.y, hi.z),
+            Vec3f::new(hi.x, lo.y, lo.z),
+        ));
+        triangles.extend(quad(
+            Vec3f::new(lo.x, hi.y, lo.z),
+            Vec3f::new(hi.x, hi.y, lo.z),
+            Vec3f::new(hi.x, hi.y, hi.z),
+            Vec3f::new(lo.x, hi.y, hi.z),
+        ));
+        triangles.extend(quad(
+            Vec3f::new(lo.x, lo.y, lo.z),
+            Vec3f::new(hi.x, lo.y, lo.z),
+            Vec3f::new(hi.x, hi.y, lo.z),
+            Vec3f::new(lo.x, hi.y, lo.z),
+        ));
+        triangles.extend(quad(
+            Vec3f::new(lo.x, lo.y, hi.z),
+            Vec3f::new(lo.x, hi.y, hi.z),
+            Vec3f::new(hi.x, hi.y, hi.z),
+            Vec3f::new(hi.x, lo.y, hi.z),
+        ));
+
+        let mut band = HashMap::new();
+        for x in -2..=2_isize {
+            band.insert(Vec3i::new(x, 0, 0), 1.0);
+        }
+
+        sign_narrow_band(&mut band, &triangles);
+
+        assert!(band[&Vec3i::new(-2, 0, 0)] > 0.0);
+        assert!(band[&Vec3i::new(0, 0, 0)] < 0.0);
+        assert!(band[&Vec3i::new(2, 0, 0)] > 0.0);
+    }
+
+    #[test]
+    fn sign_narrow_band_is_not_fooled_by_unrelated_nearby_geometry() {
+        // A single real wall at x = 0 (spanning y, z in [-3, 3]) is the
+        // only thing the ray along y = 0, z = 0 actually crosses. A second,
+        // disjoint quad at x = 4 sits close enough in space — though
+        // shifted off to y in [2, 5], out of that ray's path — that the
+        // *unsigned distance* near x = 4 still dips down before rising
+        // again: exactly the "distance decreased, then increased" shape a
+        // magnitude-based heuristic reads as a second crossing. Since sign
+        // now comes from real triangle crossings instead, the ray's one
+        // true crossing at x = 0 is the only place sign flips.
+        let wall_a = quad(
+            Vec3f::new(0.0, -3.0, -3.0),
+            Vec3f::new(0.0, 3.0, -3.0),
+            Vec3f::new(0.0, 3.0, 3.0),
+            Vec3f::new(0.0, -3.0, 3.0),
+        );
+        let wall_b = quad(
+            Vec3f::new(4.0, 2.0, -3.0),
+            Vec3f::new(4.0, 5.0, -3.0),
+            Vec3f::new(4.0, 5.0, 3.0),
+            Vec3f::new(4.0, 2.0, 3.0),
+        );
+        let mut triangles = Vec::new();
+        triangles.extend(wall_a);
+        triangles.extend(wall_b);
+
+        let mut band = HashMap::new();
+        for (a, b, c) in &triangles {
+            rasterize_triangle((*a, *b, *c), 1.0, 3.0, &mut band);
+        }
+
+        sign_narrow_band(&mut band, &triangles);
+
+        assert!(band[&Vec3i::new(-2, 0, 0)] > 0.0, "before the only real crossing, must stay exterior");
+        assert!(band[&Vec3i::new(2, 0, 0)] < 0.0, "after the only real crossing, must be interior");
+        assert!(band[&Vec3i::new(4, 0, 0)] < 0.0, "the decoy's distance dip is not a real crossing on this ray");
+        assert!(band[&Vec3i::new(6, 0, 0)] < 0.0, "past the decoy, still only one real crossing behind us");
+    }
+}
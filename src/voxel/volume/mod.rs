@@ -1,14 +1,25 @@
 pub mod builder;
+pub mod filter;
+pub mod io;
 
+mod cavities;
+mod geometry;
+mod mesh_to_sdf;
+mod resample;
+
+use self::cavities::{count_cavity_faces, find_sealed_cavities};
 use self::fast_sweep::FastSweeping;
+use self::filter::{collect_active, ActiveVoxels};
+use self::mesh_to_sdf::{rasterize_triangle, sign_narrow_band};
+use self::resample::{inverse_transform_point, uniform_scale};
 use self::visitors::value_mut_visitor::ValueMutVisitor;
 use crate::voxel::*;
-use crate::{dynamic_vdb, helpers::aliases::Vec3f};
-use std::fs::File;
-use std::marker::PhantomData;
-use std::path::Path;
+use crate::{
+    dynamic_vdb,
+    helpers::aliases::{Mat4f, Vec3f},
+};
+use std::collections::HashMap;
 use volume::visitors::keep_sign_change_cubes::KeepSignChangeCubes;
-use volume::visitors::min_max_index_visitor::MinMaxIdxVisitor;
 
 pub(super) type VolumeGrid = dynamic_vdb!(f32, par 5, 4, 3);
 
@@ -62,6 +73,46 @@ impl Volume {
         Self { grid, voxel_size }
     }
 
+    ///
+    /// Voxelizes a triangle soup into a signed narrow-band SDF. `faces` indexes
+    /// into `vertices`. Orientation-independent: sign is recovered from the
+    /// parity of each voxel's ray intersections against the mesh itself,
+    /// rather than from face normals, so self-intersecting, non-manifold or
+    /// degenerate input is tolerated as long as the surface is approximately
+    /// closed.
+    ///
+    pub fn from_mesh(
+        vertices: &[Vec3f],
+        faces: &[[usize; 3]],
+        voxel_size: f32,
+        narrow_band_width: usize,
+    ) -> Self {
+        let band = (narrow_band_width + 1) as f32 * voxel_size;
+
+        let mut distances: HashMap<Vec3i, f32> = HashMap::new();
+        let mut triangles = Vec::with_capacity(faces.len());
+        for face in faces {
+            let triangle = (
+                vertices[face[0]] / voxel_size,
+                vertices[face[1]] / voxel_size,
+                vertices[face[2]] / voxel_size,
+            );
+            rasterize_triangle(triangle, 1.0, band / voxel_size, &mut distances);
+            triangles.push(triangle);
+        }
+
+        sign_narrow_band(&mut distances, &triangles);
+
+        let mut grid = VolumeGrid::empty(Vec3i::zeros());
+        for (idx, dist) in &distances {
+            grid.insert(idx, *dist * voxel_size);
+        }
+
+        grid.flood_fill();
+
+        Self { grid, voxel_size }
+    }
+
     pub fn union(mut self, mut other: Self) -> Self {
         self.grid.flood_fill();
         other.grid.flood_fill();
@@ -99,102 +150,332 @@ impl Volume {
 
         println!("Fast sweeping took: {:?}", time.elapsed());
 
-        // volume_to_nrrd(&self.grid, Path::new("offset.nrrd"));
-
         let mut offset = ValueMutVisitor::<VolumeGrid, _>::from_fn(|v| *v -= distance);
         self.grid.visit_values_mut(&mut offset);
 
         self
     }
 
-    pub(in crate::voxel) fn grid(&self) -> &VolumeGrid {
-        // HIDE
-        &self.grid
+    ///
+    /// Resamples the SDF onto a grid with a different `new_voxel_size`, so it
+    /// can be combined with a volume of another resolution via `union`/
+    /// `intersect`/`subtract`. Destination voxels are trilinearly interpolated
+    /// from a snapshot of the source's active voxels and tiles; only voxels whose
+    /// interpolated magnitude stays within the narrow band are kept.
+    ///
+    pub fn resample(&self, new_voxel_size: f32) -> Self {
+        let source_active = collect_active(&self.grid);
+        let band_width = narrow_band_width(&source_active);
+
+        let (dest_min, dest_max) = destination_bounds(
+            &source_active,
+            self.voxel_size,
+            new_voxel_size,
+            band_width,
+            |world_pos| world_pos,
+        );
+
+        let ratio = new_voxel_size / self.voxel_size;
+        let grid = resample::resample_grid(
+            &source_active,
+            band_width,
+            band_width,
+            1.0,
+            dest_min,
+            dest_max,
+            |dest_idx| dest_idx.cast() * ratio,
+        );
+
+        Self {
+            grid,
+            voxel_size: new_voxel_size,
+        }
     }
-}
 
-impl Clone for Volume {
-    fn clone(&self) -> Self {
+    ///
+    /// Applies an affine transform to the SDF, producing a new grid sampled at
+    /// the same `voxel_size`. Each destination voxel is mapped back through
+    /// the inverse transform into source index space, trilinearly
+    /// interpolated, and the resulting distance is rescaled by the
+    /// transform's uniform scale factor so the result stays a valid SDF.
+    /// `transform` is assumed to carry no shear.
+    ///
+    pub fn transform(&self, transform: &Mat4f) -> Self {
+        let source_active = collect_active(&self.grid);
+        let band_width = narrow_band_width(&source_active);
+        let scale = uniform_scale(transform);
+
+        let (dest_min, dest_max) = destination_bounds(
+            &source_active,
+            self.voxel_size,
+            self.voxel_size,
+            band_width,
+            |world_pos| resample::transform_point(transform, world_pos),
+        );
+
+        let grid = resample::resample_grid(
+            &source_active,
+            band_width,
+            band_width,
+            scale,
+            dest_min,
+            dest_max,
+            |dest_idx| {
+                let dest_world = dest_idx.cast() * self.voxel_size;
+                inverse_transform_point(transform, dest_world) / self.voxel_size
+            },
+        );
+
         Self {
-            grid: self.grid.clone(),
+            grid,
             voxel_size: self.voxel_size,
         }
     }
-}
 
-fn volume_to_nrrd(volume: &VolumeGrid, path: &Path) {
-    use rusty_nrrd::*;
+    ///
+    /// Seals enclosed cavities: voxel regions classified as outside (positive
+    /// sign) that are not connected to the true exterior, such as air pockets
+    /// left by noisy mesh conversion or CSG. Found via a flood fill seeded
+    /// from the grid's bounding-box boundary; every outside-classified
+    /// narrow-band voxel the fill doesn't reach is flipped to solid
+    /// (negative), then `flood_fill()` is re-run. Voxels covered by uniform
+    /// background tiles are not flipped individually.
+    ///
+    pub fn fill_cavities(mut self) -> Self {
+        self.grid.flood_fill();
+        let active = collect_active(&self.grid);
+        let (min, max) = active_bounds(&active);
 
-    let mut min_max_idx = MinMaxIdxVisitor::<VolumeGrid>::new();
-    volume.visit_leafs(&mut min_max_idx);
-    let MinMaxIdxVisitor { min, max, .. } = min_max_idx;
+        let cavities = find_sealed_cavities(&active, min, max);
+        if cavities.is_empty() {
+            return self;
+        }
 
-    println!("Min: {:?}, Max: {:?}", min, max);
+        // Write flips back keyed by index: `visit_values_mut`'s traversal order
+        // has no relation to a `HashMap`'s, so a positional write-back would
+        // flip an arbitrary voxel instead of the cavity we actually found.
+        for idx in &cavities {
+            if let Some(value) = active.get(idx) {
+                self.grid.insert(idx, -value);
+            }
+        }
 
-    let min = Vec3i::new(-152, -120, -16);
-    let max = Vec3i::new(152, 120, 272);
+        self.grid.flood_fill();
 
-    let sizes = max - min + Vec3i::new(1, 1, 1);
-    let background = 1000.0;
-    let image = Image::<f32, 3>::new(
-        background,
-        [sizes.x as usize, sizes.y as usize, sizes.z as usize],
-    );
+        self
+    }
 
-    let mut visitor = VolumeToImage::<VolumeGrid> {
-        image,
-        min,
-        background,
-        _tree: PhantomData,
+    ///
+    /// Total surface area enclosing sealed cavities (see [`Self::fill_cavities`]):
+    /// the count of voxel faces between a solid voxel and an unreached cavity
+    /// voxel, scaled to world units.
+    ///
+    pub fn interior_surface_area(&self) -> f32 {
+        let active = collect_active(&self.grid);
+        let (min, max) = active_bounds(&active);
+
+        let cavities = find_sealed_cavities(&active, min, max);
+        let faces = count_cavity_faces(&active, &cavities);
+
+        faces as f32 * self.voxel_size * self.voxel_size
+    }
+
+    ///
+    /// Writes this volume to `path` in the given [`io::VolumeFormat`].
+    ///
+    pub fn save(&self, path: &std::path::Path, format: io::VolumeFormat) -> std::io::Result<()> {
+        io::save(&self.grid, self.voxel_size, path, format)
+    }
+
+    ///
+    /// Reads a volume previously written with [`Self::save`] in the given
+    /// [`io::VolumeFormat`].
+    ///
+    pub fn load(path: &std::path::Path, format: io::VolumeFormat) -> std::io::Result<Self> {
+        let (grid, voxel_size) = io::load(path, format)?;
+        Ok(Self { grid, voxel_size })
+    }
+
+    pub(in crate::voxel) fn grid(&self) -> &VolumeGrid {
+        // HIDE
+        &self.grid
+    }
+}
+
+/// Grid-index bounding box covering every active voxel, padded by one voxel
+/// so a boundary-seeded flood fill is guaranteed to start outside the shape.
+fn active_bounds(active: &ActiveVoxels) -> (Vec3i, Vec3i) {
+    let (min, max) = index_bounds(active.keys());
+    (min - Vec3i::new(1, 1, 1), max + Vec3i::new(1, 1, 1))
+}
+
+/// Bounding box of `indices`, seeded from the first element so off-origin
+/// geometry doesn't bias/balloon the box toward `(0, 0, 0)`. Empty input
+/// collapses to a box at the origin.
+fn index_bounds(mut indices: impl Iterator<Item = Vec3i>) -> (Vec3i, Vec3i) {
+    let Some(first) = indices.next() else {
+        return (Vec3i::zeros(), Vec3i::zeros());
     };
 
-    volume.visit_leafs(&mut visitor);
+    let mut min = first;
+    let mut max = first;
+    for idx in indices {
+        min = min.inf(&idx);
+        max = max.sup(&idx);
+    }
 
-    let nrrd = Nrrd::try_from(&visitor.image).unwrap();
-    write_nrrd(&nrrd, File::create(path).unwrap()).expect("write nrrd");
+    (min, max)
 }
 
-use rusty_nrrd::*;
+/// Largest magnitude among the active narrow-band values, used as the
+/// effective narrow-band width (in world units) when resampling.
+fn narrow_band_width(active: &ActiveVoxels) -> f32 {
+    active.values().fold(0.0_f32, |acc, v| acc.max(v.abs()))
+}
 
-struct VolumeToImage<T: TreeNode<Value = f32>> {
-    image: Image<f32, 3>,
-    min: Vec3i,
-    background: f32,
-    _tree: PhantomData<T>,
+/// Destination index-space bounding box that covers the source's active
+/// region (mapped through `map_world`), padded by `band_width` (in
+/// destination voxels, rounded up) plus one voxel.
+///
+/// All 8 corners of the source AABB are mapped, not just the 2 diagonal
+/// ones: that shortcut only covers axis-aligned scaling, but `map_world` can
+/// carry an arbitrary rotation (see `Volume::transform`'s general `Mat4f`),
+/// in which case the other 6 corners can land outside the 2-corner box and
+/// the result silently clips part of the SDF.
+fn destination_bounds(
+    active: &ActiveVoxels,
+    source_voxel_size: f32,
+    dest_voxel_size: f32,
+    band_width: f32,
+    map_world: impl Fn(Vec3f) -> Vec3f,
+) -> (Vec3i, Vec3i) {
+    let (src_min, src_max) = index_bounds(active.keys());
+
+    let corners = [
+        Vec3f::new(src_min.x as f32, src_min.y as f32, src_min.z as f32),
+        Vec3f::new(src_max.x as f32, src_min.y as f32, src_min.z as f32),
+        Vec3f::new(src_min.x as f32, src_max.y as f32, src_min.z as f32),
+        Vec3f::new(src_max.x as f32, src_max.y as f32, src_min.z as f32),
+        Vec3f::new(src_min.x as f32, src_min.y as f32, src_max.z as f32),
+        Vec3f::new(src_max.x as f32, src_min.y as f32, src_max.z as f32),
+        Vec3f::new(src_min.x as f32, src_max.y as f32, src_max.z as f32),
+        Vec3f::new(src_max.x as f32, src_max.y as f32, src_max.z as f32),
+    ];
+
+    let mut dest_min = Vec3f::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut dest_max = Vec3f::new(f32::MIN, f32::MIN, f32::MIN);
+    for corner in corners {
+        let world = corner * source_voxel_size;
+        let mapped = map_world(world) / dest_voxel_size;
+        dest_min = dest_min.inf(&mapped);
+        dest_max = dest_max.sup(&mapped);
+    }
+
+    let padding = (band_width / dest_voxel_size).ceil() as isize + 1;
+    let min = dest_min.map(|x| x.floor() as isize) - Vec3i::new(padding, padding, padding);
+    let max = dest_max.map(|x| x.ceil() as isize) + Vec3i::new(padding, padding, padding);
+
+    (min, max)
 }
 
-impl<T: TreeNode<Value = f32>> Visitor<T::Leaf> for VolumeToImage<T> {
-    fn tile(&mut self, tile: Tile<<T::Leaf as TreeNode>::Value>) {
-        for x in 0..tile.size {
-            for y in 0..tile.size {
-                for z in 0..tile.size {
-                    // if tile.value.sign() == Sign::Positive {
-                    //     self.image[[x, y, z]] = 1.0;
-                    // } else {
-                    //     self.image[[x, y, z]] = -1.0;
-                    // }
-
-                    self.image[[x, y, z]] = tile.value;
-                }
-            }
+impl Clone for Volume {
+    fn clone(&self) -> Self {
+        Self {
+            grid: self.grid.clone(),
+            voxel_size: self.voxel_size,
         }
     }
+}
 
-    fn dense(&mut self, dense: &T::Leaf) {
-        for x in 0..T::Leaf::resolution() {
-            for y in 0..T::Leaf::resolution() {
-                for z in 0..T::Leaf::resolution() {
-                    let idx = dense.origin() + Vec3i::new(x as isize, y as isize, z as isize);
-                    let shifted = idx - self.min;
-                    let shifted_usize = shifted.map(|x| x as usize);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_bounds_seeds_from_first_key_not_the_origin() {
+        // All indices sit far from (0, 0, 0); a zero-seeded bounding box
+        // would balloon out to include the origin instead of tightly
+        // wrapping the actual data.
+        let indices = [
+            Vec3i::new(100, 200, 300),
+            Vec3i::new(105, 202, 303),
+            Vec3i::new(103, 198, 301),
+        ];
+
+        let (min, max) = index_bounds(indices.iter().copied());
+
+        assert_eq!(min, Vec3i::new(100, 198, 300));
+        assert_eq!(max, Vec3i::new(105, 202, 303));
+    }
 
-                    let val = dense.at(&idx).copied();
-                    // let val = dense.at(&idx).copied().map(|v| if v.sign() == Sign::Positive { 1.0 } else { -1.0 });
+    #[test]
+    fn index_bounds_of_empty_iterator_collapses_to_origin() {
+        assert_eq!(
+            index_bounds(std::iter::empty()),
+            (Vec3i::zeros(), Vec3i::zeros())
+        );
+    }
 
-                    self.image[[shifted_usize.x, shifted_usize.y, shifted_usize.z]] =
-                        val.unwrap_or(self.background);
-                }
-            }
-        }
+    #[test]
+    fn from_mesh_of_off_origin_geometry_stays_tight_around_the_mesh() {
+        // A unit cube far from the origin: a zero-seeded bounding box would
+        // have pulled `min` all the way back to (0, 0, 0), wasting the
+        // narrow band on empty space between the origin and the mesh.
+        let offset = Vec3f::new(1000.0, 1000.0, 1000.0);
+        let vertices = vec![
+            offset + Vec3f::new(-0.5, -0.5, -0.5),
+            offset + Vec3f::new(0.5, -0.5, -0.5),
+            offset + Vec3f::new(0.5, 0.5, -0.5),
+            offset + Vec3f::new(-0.5, 0.5, -0.5),
+            offset + Vec3f::new(-0.5, -0.5, 0.5),
+            offset + Vec3f::new(0.5, -0.5, 0.5),
+            offset + Vec3f::new(0.5, 0.5, 0.5),
+            offset + Vec3f::new(-0.5, 0.5, 0.5),
+        ];
+        let faces = [
+            [0, 1, 2],
+            [0, 2, 3],
+            [4, 6, 5],
+            [4, 7, 6],
+            [0, 4, 5],
+            [0, 5, 1],
+            [1, 5, 6],
+            [1, 6, 2],
+            [2, 6, 7],
+            [2, 7, 3],
+            [3, 7, 4],
+            [3, 4, 0],
+        ];
+
+        let volume = Volume::from_mesh(&vertices, &faces, 0.1, 2);
+
+        let active = collect_active(&volume.grid);
+        let (min, max) = index_bounds(active.keys());
+
+        // The mesh spans roughly index 9995..10005 at this voxel size
+        // (about 9992..10008 with its 3-voxel band); a zero-seeded box would
+        // instead report min near 0.
+        assert!(min.x > 9000, "min.x = {} was pulled toward the origin", min.x);
+        assert!(max.x < 11000, "max.x = {} extends far past the mesh", max.x);
+    }
+
+    #[test]
+    fn destination_bounds_covers_all_corners_under_a_rotating_map() {
+        // A 90-degree rotation about Z (x, y, z) -> (-y, x, z) swings the
+        // non-seeded corners of the AABB well outside the box spanned by
+        // just the two diagonal corners. Mapping all 8 corners must still
+        // produce a box that contains every rotated corner.
+        let mut active = ActiveVoxels::default();
+        active.insert(Vec3i::new(0, 0, 0), 1.0);
+        active.insert(Vec3i::new(10, 4, 2), 1.0);
+
+        let rotate = |p: Vec3f| Vec3f::new(-p.y, p.x, p.z);
+
+        let (min, max) = destination_bounds(&active, 1.0, 1.0, 0.0, rotate);
+
+        // Source AABB is [0, 10] x [0, 4] x [0, 2]; rotated it spans
+        // [-4, 0] x [0, 10] x [0, 2], with 1 voxel of padding on each side.
+        assert!(min.x <= -4, "min.x = {} doesn't cover the rotated corners", min.x);
+        assert!(max.y >= 10, "max.y = {} doesn't cover the rotated corners", max.y);
     }
 }
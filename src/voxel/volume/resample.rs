@@ -0,0 +1,146 @@
+use super::filter::ActiveVoxels;
+use super::VolumeGrid;
+use crate::helpers::aliases::{Mat4f, Vec3f};
+use crate::voxel::Vec3i;
+
+/// Builds a new [`VolumeGrid`] by sampling `source_active` through
+/// `dest_to_source_pos`, which maps a destination grid index to a position in
+/// source index space (the units `sample_trilinear` expects). Only
+/// destination voxels whose interpolated magnitude falls inside `band_width`
+/// (world units) are instantiated.
+pub(super) fn resample_grid(
+    source_active: &ActiveVoxels,
+    background: f32,
+    band_width: f32,
+    distance_scale: f32,
+    dest_min: Vec3i,
+    dest_max: Vec3i,
+    dest_to_source_pos: impl Fn(Vec3i) -> Vec3f,
+) -> Box<VolumeGrid> {
+    let mut grid = VolumeGrid::empty(Vec3i::zeros());
+
+    for x in dest_min.x..=dest_max.x {
+        for y in dest_min.y..=dest_max.y {
+            for z in dest_min.z..=dest_max.z {
+                let idx = Vec3i::new(x, y, z);
+                let source_pos = dest_to_source_pos(idx);
+                let value = sample_trilinear(source_active, source_pos, background) * distance_scale;
+
+                if value.abs() >= band_width {
+                    continue;
+                }
+
+                grid.insert(&idx, value);
+            }
+        }
+    }
+
+    grid.prune();
+
+    grid
+}
+
+/// Trilinearly interpolates `active` (a sparse narrow-band value map, as produced
+/// by a leaf walk of the source grid) at the source-space position `pos`,
+/// measured in source voxel units. Missing lattice corners fall back to
+/// `background`, which should be a value outside the narrow band (e.g. the
+/// source's narrow band width) so resampling never invents a false crossing.
+pub(super) fn sample_trilinear(active: &ActiveVoxels, pos: Vec3f, background: f32) -> f32 {
+    let base = Vec3i::new(
+        pos.x.floor() as isize,
+        pos.y.floor() as isize,
+        pos.z.floor() as isize,
+    );
+    let frac = Vec3f::new(pos.x - base.x as f32, pos.y - base.y as f32, pos.z - base.z as f32);
+
+    let at = |offset: Vec3i| -> f32 { active.get(&(base + offset)).unwrap_or(background) };
+
+    let c000 = at(Vec3i::new(0, 0, 0));
+    let c100 = at(Vec3i::new(1, 0, 0));
+    let c010 = at(Vec3i::new(0, 1, 0));
+    let c110 = at(Vec3i::new(1, 1, 0));
+    let c001 = at(Vec3i::new(0, 0, 1));
+    let c101 = at(Vec3i::new(1, 0, 1));
+    let c011 = at(Vec3i::new(0, 1, 1));
+    let c111 = at(Vec3i::new(1, 1, 1));
+
+    let c00 = c000 + (c100 - c000) * frac.x;
+    let c10 = c010 + (c110 - c010) * frac.x;
+    let c01 = c001 + (c101 - c001) * frac.x;
+    let c11 = c011 + (c111 - c011) * frac.x;
+
+    let c0 = c00 + (c10 - c00) * frac.y;
+    let c1 = c01 + (c11 - c01) * frac.y;
+
+    c0 + (c1 - c0) * frac.z
+}
+
+/// Maps a world-space point through the inverse of `transform`.
+pub(super) fn inverse_transform_point(transform: &Mat4f, p: Vec3f) -> Vec3f {
+    let inverse = transform
+        .try_inverse()
+        .expect("Volume::transform matrix must be invertible");
+    transform_point(&inverse, p)
+}
+
+pub(super) fn transform_point(transform: &Mat4f, p: Vec3f) -> Vec3f {
+    let x = transform[(0, 0)] * p.x + transform[(0, 1)] * p.y + transform[(0, 2)] * p.z + transform[(0, 3)];
+    let y = transform[(1, 0)] * p.x + transform[(1, 1)] * p.y + transform[(1, 2)] * p.z + transform[(1, 3)];
+    let z = transform[(2, 0)] * p.x + transform[(2, 1)] * p.y + transform[(2, 2)] * p.z + transform[(2, 3)];
+    let w = transform[(3, 0)] * p.x + transform[(3, 1)] * p.y + transform[(3, 2)] * p.z + transform[(3, 3)];
+
+    Vec3f::new(x / w, y / w, z / w)
+}
+
+/// The transform's uniform scale factor, read off the length of its first
+/// column. Callers are expected to pass affine transforms without shear, as
+/// documented on `Volume::transform`.
+pub(super) fn uniform_scale(transform: &Mat4f) -> f32 {
+    let column = Vec3f::new(transform[(0, 0)], transform[(1, 0)], transform[(2, 0)]);
+    column.norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::filter::collect_active;
+    use super::super::Volume;
+    use crate::helpers::aliases::Vec3f;
+    use crate::voxel::Vec3i;
+
+    #[test]
+    fn resample_to_a_coarser_voxel_size_keeps_the_zero_crossing_at_the_sphere_radius() {
+        // Along the +x axis, `p.norm() - radius` is exactly `|x| - radius`,
+        // i.e. linear, so trilinear interpolation through a resample should
+        // reproduce it exactly (up to float error): the zero crossing must
+        // stay at the sphere's radius regardless of voxel size.
+        let radius = 2.0;
+        let volume = Volume::from_fn(
+            0.1,
+            Vec3f::new(-3.0, -3.0, -3.0),
+            Vec3f::new(3.0, 3.0, 3.0),
+            3,
+            |p| p.norm() - radius,
+        );
+
+        let resampled = volume.resample(0.25);
+        let active = collect_active(resampled.grid());
+
+        // dest index 8 at voxel size 0.25 lands exactly on world x = 2.0 = radius.
+        let inside = active.get(&Vec3i::new(7, 0, 0)).expect("just inside the sphere should be active");
+        let crossing = active.get(&Vec3i::new(8, 0, 0)).expect("the radius itself should be active");
+        let outside = active.get(&Vec3i::new(9, 0, 0)).expect("just outside the sphere should be active");
+
+        assert!(inside < 0.0, "voxel inside the sphere should stay negative, got {inside}");
+        assert!(crossing.abs() < 0.01, "voxel at the radius should be ~0, got {crossing}");
+        assert!(outside > 0.0, "voxel outside the sphere should stay positive, got {outside}");
+
+        assert!(
+            (inside - -0.25).abs() < 0.01,
+            "interpolated distance should match the analytic |x| - radius, got {inside}"
+        );
+        assert!(
+            (outside - 0.25).abs() < 0.01,
+            "interpolated distance should match the analytic |x| - radius, got {outside}"
+        );
+    }
+}